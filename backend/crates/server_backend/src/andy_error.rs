@@ -28,4 +28,16 @@ pub enum AndyError {
     HttpError(#[from] hyper::http::Error),
     #[error("http invalid header")]
     HttpInvalidHeader(#[from] hyper::header::InvalidHeaderValue),
+    #[error("jwt error")]
+    Jwt(#[from] jsonwebtoken::errors::Error),
+    #[error("sqlite error")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("http client error")]
+    Reqwest(#[from] reqwest::Error),
+    #[error("unknown oauth provider")]
+    OAuthProviderNotConfigured,
+    #[error("invalid or expired oauth state")]
+    OAuthInvalidState,
+    #[error("invalid public id")]
+    InvalidPublicId,
 }
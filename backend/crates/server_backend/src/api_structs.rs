@@ -0,0 +1,225 @@
+//! Request/response bodies for every JSON endpoint the server dispatches,
+//! plus the path each one lives at. Kept in one file so `server::mod` and
+//! `server::openapi` always agree on the shape and address of an endpoint.
+//!
+//! Every type here derives `utoipa::ToSchema` so `openapi::spec_json()` can
+//! describe it with a real, named schema instead of an opaque `{"type":
+//! "object"}` placeholder - that's what lets a client generate a typed SDK
+//! from `GET /openapi.json` instead of hand-guessing field names.
+
+pub const ENDPOINT_CREATE_CARD_DECK: &str = "/create_card_deck";
+pub const ENDPOINT_DELETE_CARD_DECK: &str = "/delete_card_deck";
+pub const ENDPOINT_CREATE_CARD: &str = "/create_card";
+pub const ENDPOINT_DELETE_CARD: &str = "/delete_card";
+pub const ENDPOINT_NEW_USER: &str = "/new_user";
+pub const ENDPOINT_LIST_CARD_DECKS: &str = "/list_card_decks";
+pub const ENDPOINT_LIST_CARDS: &str = "/list_cards";
+pub const ENDPOINT_LOGIN: &str = "/login";
+pub const ENDPOINT_CREATE_DECK_PDF: &str = "/create_deck_pdf";
+pub const ENDPOINT_DELETE_USER: &str = "/delete_user";
+pub const ENDPOINT_CHANGE_PASSWORD: &str = "/change_password";
+pub const ENDPOINT_AI_TEST: &str = "/ai_test";
+pub const ENDPOINT_GET_DECK: &str = "/get_deck";
+pub const ENDPOINT_SEARCH_DECKS: &str = "/search_decks";
+pub const ENDPOINT_EDIT_CARD: &str = "/edit_card";
+pub const ENDPOINT_OAUTH_START: &str = "/oauth_start";
+pub const ENDPOINT_OAUTH_CALLBACK: &str = "/oauth_callback";
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct Card {
+    pub question: String,
+    pub answer: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct CardDeck {
+    pub deck_id: String,
+    pub name: String,
+    pub num_cards: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct LoginResponse {
+    pub access_token: String,
+    pub user_id: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct NewUser {
+    pub user_name: String,
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct DeleteUser {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct ChangePassword {
+    pub email: String,
+    pub old_password: String,
+    pub new_password: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct CreateCardDeck {
+    pub access_token: String,
+    pub deck_name: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct CreateCardDeckResponse {
+    pub deck_id: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct DeleteCardDeck {
+    pub access_token: String,
+    pub deck_id: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct GetDeckRequest {
+    pub user_id: String,
+    pub deck_id: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct GetDeckResponse {
+    pub name: String,
+    pub cards: Vec<Card>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct ListCardDecks {
+    pub access_token: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct ListCardDecksResponse {
+    pub decks: Vec<CardDeck>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct ListCards {
+    pub user_id: String,
+    pub deck_id: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct ListCardsResponse {
+    pub cards: Vec<Card>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct CreateCard {
+    pub access_token: String,
+    pub deck_id: String,
+    pub question: String,
+    pub answer: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct DeleteCard {
+    pub access_token: String,
+    pub deck_id: String,
+    pub card_index: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct EditCard {
+    pub access_token: String,
+    pub deck_id: String,
+    pub card_index: u32,
+    pub new_question: String,
+    pub new_answer: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct SearchDecksRequest {
+    pub prompt: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct SearchDecksResponse {
+    pub decks: Vec<CardDeck>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct UploadPdf {
+    pub access_token: String,
+    pub file_bytes_base64: String,
+    pub deck_name: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct UploadPdfResponse {
+    pub deck_id: String,
+    pub card_count: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct AiPromptTest {
+    pub prompt: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct OAuthStartRequest {
+    pub provider: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct OAuthStartResponse {
+    pub authorize_url: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct OAuthCallbackRequest {
+    pub state: String,
+    pub code: String,
+}
+
+/// Registers every request/response type above as a named component schema,
+/// so `operation_for` in `server::openapi` can reference them by name
+/// instead of inlining an untyped placeholder.
+pub fn register_schemas(
+    builder: utoipa::openapi::ComponentsBuilder,
+) -> utoipa::openapi::ComponentsBuilder {
+    builder
+        .schema_from::<Card>()
+        .schema_from::<CardDeck>()
+        .schema_from::<LoginRequest>()
+        .schema_from::<LoginResponse>()
+        .schema_from::<NewUser>()
+        .schema_from::<DeleteUser>()
+        .schema_from::<ChangePassword>()
+        .schema_from::<CreateCardDeck>()
+        .schema_from::<CreateCardDeckResponse>()
+        .schema_from::<DeleteCardDeck>()
+        .schema_from::<GetDeckRequest>()
+        .schema_from::<GetDeckResponse>()
+        .schema_from::<ListCardDecks>()
+        .schema_from::<ListCardDecksResponse>()
+        .schema_from::<ListCards>()
+        .schema_from::<ListCardsResponse>()
+        .schema_from::<CreateCard>()
+        .schema_from::<DeleteCard>()
+        .schema_from::<EditCard>()
+        .schema_from::<SearchDecksRequest>()
+        .schema_from::<SearchDecksResponse>()
+        .schema_from::<UploadPdf>()
+        .schema_from::<UploadPdfResponse>()
+        .schema_from::<AiPromptTest>()
+        .schema_from::<OAuthStartRequest>()
+        .schema_from::<OAuthStartResponse>()
+        .schema_from::<OAuthCallbackRequest>()
+}
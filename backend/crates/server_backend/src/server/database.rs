@@ -1,59 +1,81 @@
+use crate::server::public_id;
+use crate::server::storage::{Card, CardDeck, StorageBackend, UserEntry};
 use crate::AndyError;
-use redb::ReadableTable;
-use sha2::Digest;
-use std::hash::Hasher;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand_core::OsRng;
 
-const SHA265_NUM_BYTES: usize = 100;
+/// How long an issued session token stays valid for.
+const SESSION_TTL_SECONDS: u64 = 60 * 60 * 24;
+/// How long an OAuth2 `state` nonce stays valid for before the callback must
+/// have used it.
+const OAUTH_STATE_TTL_SECONDS: u64 = 10 * 60;
 
-#[serde_with::serde_as]
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
-struct UserEntry {
-    username: String,
-    user_id: u64,
-    email: String,
-    #[serde_as(as = "serde_with::Bytes")]
-    //idk prolly serde will fix this const generics in the future
-    password_hash: [u8; SHA265_NUM_BYTES],
-    signup_time: u64,
-}
-
-#[derive(serde::Serialize, serde::Deserialize, Debug)]
-struct CardDeck {
-    creation_time: u64,
-    cards: Vec<Card>,
-    name: String,
-}
-
-#[derive(serde::Serialize, serde::Deserialize, Debug)]
-struct Card {
-    question: String,
-    answer: String,
+struct SessionClaims {
+    sub: u64,
+    iat: u64,
+    exp: u64,
 }
 
 pub struct Database {
-    db: redb::Database,
+    backend: Box<dyn StorageBackend>,
 }
 
 impl Database {
-    const USERS_TABLE: redb::TableDefinition<'static, &'static str, UserEntry> =
-        redb::TableDefinition::new("users");
-    const DECKS_TABLE: redb::TableDefinition<'static, (u64, u64), CardDeck> =
-        redb::TableDefinition::new("decks");
+    pub fn new(db_path: std::path::PathBuf) -> Result<Self, AndyError> {
+        Ok(Self {
+            backend: Box::new(crate::server::storage::RedbBackend::new(db_path)?),
+        })
+    }
 
-    pub fn validate_token(&self, _token: String) -> Result<u64, AndyError> {
-        todo!()
+    pub fn with_backend(backend: Box<dyn StorageBackend>) -> Self {
+        Self { backend }
     }
 
-    pub fn new(db_path: std::path::PathBuf) -> Result<Self, AndyError> {
-        let db = redb::Database::create(db_path)?;
-        {
-            //create tables
-            let write_txn = db.begin_write()?;
-            write_txn.open_table(Self::USERS_TABLE)?;
-            write_txn.open_table(Self::DECKS_TABLE)?;
-            write_txn.commit()?;
-        }
-        Ok(Self { db })
+    pub fn validate_token(&self, token: String, jwt_secret: &[u8]) -> Result<u64, AndyError> {
+        let data = jsonwebtoken::decode::<SessionClaims>(
+            &token,
+            &jsonwebtoken::DecodingKey::from_secret(jwt_secret),
+            &jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::HS256),
+        )
+        .map_err(|_| AndyError::BadAccessToken)?;
+
+        Ok(data.claims.sub)
+    }
+
+    pub fn new_session(
+        &self,
+        user_id: u64,
+        password: String,
+        jwt_secret: &[u8],
+    ) -> Result<String, AndyError> {
+        let user = self
+            .backend
+            .iter_users()?
+            .into_iter()
+            .find(|user| user.user_id == user_id)
+            .ok_or(AndyError::UserDoesNotExist)?;
+
+        verify_password(user.password_hash.as_deref(), &password)?;
+        self.issue_session_token(user_id, jwt_secret)
+    }
+
+    fn issue_session_token(&self, user_id: u64, jwt_secret: &[u8]) -> Result<String, AndyError> {
+        let now = get_current_unix_time_seconds();
+        let claims = SessionClaims {
+            sub: user_id,
+            iat: now,
+            exp: now + SESSION_TTL_SECONDS,
+        };
+
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::HS256),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(jwt_secret),
+        )?;
+
+        Ok(token)
     }
 
     pub fn new_user(
@@ -62,41 +84,153 @@ impl Database {
         email: String,
         password: String,
     ) -> Result<(), AndyError> {
-        let user_id = hash(&user_name); //todo idk
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(Self::USERS_TABLE)?;
-            table.insert(
-                user_name.as_str(),
-                UserEntry {
-                    username: user_name.clone(),
-                    user_id,
-                    email,
-                    password_hash: sha256_hash(password.as_bytes()),
-                    signup_time: get_current_unix_time_seconds(),
-                },
-            )?;
-        }
-        write_txn.commit()?;
+        let password_hash = Some(hash_password(&password)?);
+        let signup_time = get_current_unix_time_seconds();
+        let username_key = user_name.clone();
+        self.backend.create_user(
+            &username_key,
+            Box::new(move |user_id| UserEntry {
+                username: user_name,
+                user_id,
+                email,
+                password_hash,
+                signup_time,
+                oauth_provider: None,
+                oauth_subject: None,
+            }),
+        )?;
         Ok(())
     }
 
-    pub fn new_card_deck(&self, user_id: u64, deck_name: String) -> Result<(), AndyError> {
-        let deck_id = hash(&deck_name);
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(Self::DECKS_TABLE)?;
-            table.insert(
-                (user_id, deck_id),
-                CardDeck {
-                    creation_time: get_current_unix_time_seconds(),
-                    cards: vec![],
-                    name: deck_name,
-                },
-            )?;
+    pub fn delete_user(&self, email: String, password: String) -> Result<(), AndyError> {
+        let user = self
+            .backend
+            .iter_users()?
+            .into_iter()
+            .find(|user| user.email == email)
+            .ok_or(AndyError::UserDoesNotExist)?;
+
+        verify_password(user.password_hash.as_deref(), &password)?;
+        self.backend.delete_user(&user.username)
+    }
+
+    pub fn change_password(
+        &self,
+        email: String,
+        old_password: String,
+        new_password: String,
+    ) -> Result<(), AndyError> {
+        let mut user = self
+            .backend
+            .iter_users()?
+            .into_iter()
+            .find(|user| user.email == email)
+            .ok_or(AndyError::UserDoesNotExist)?;
+
+        verify_password(user.password_hash.as_deref(), &old_password)?;
+        user.password_hash = Some(hash_password(&new_password)?);
+        self.backend.put_user(&user.username.clone(), &user)
+    }
+
+    /// Begins an OAuth2 login: mints a one-time `state` nonce tied to
+    /// `provider` for the client to round-trip through the provider's
+    /// authorize endpoint and back to our callback.
+    pub fn begin_oauth_state(&self, provider: String) -> Result<String, AndyError> {
+        let state = generate_state_nonce();
+        self.backend
+            .put_oauth_state(&state, &provider, get_current_unix_time_seconds())?;
+        Ok(state)
+    }
+
+    /// Consumes a `state` nonce minted by `begin_oauth_state`, returning the
+    /// provider it was issued for if it's still within its TTL.
+    pub fn consume_oauth_state(&self, state: String) -> Result<String, AndyError> {
+        let (provider, created_at) = self
+            .backend
+            .take_oauth_state(&state)?
+            .ok_or(AndyError::OAuthInvalidState)?;
+
+        if get_current_unix_time_seconds().saturating_sub(created_at) > OAUTH_STATE_TTL_SECONDS {
+            return Err(AndyError::OAuthInvalidState);
         }
-        write_txn.commit()?;
-        Ok(())
+
+        Ok(provider)
+    }
+
+    /// Resolves an OAuth2 userinfo response to a local user, linking to an
+    /// existing account by verified email or provisioning a fresh
+    /// passwordless one.
+    ///
+    /// `email_verified` must reflect the provider's own assertion that it
+    /// has verified ownership of `email` - an unverified email is never
+    /// trusted to link to an existing account, since that would let an
+    /// attacker claim a victim's account by supplying a matching but
+    /// unverified email from a provider (or misconfigured provider) they
+    /// control.
+    pub fn oauth_login(
+        &self,
+        provider: String,
+        external_id: String,
+        email: String,
+        email_verified: bool,
+    ) -> Result<u64, AndyError> {
+        let users = self.backend.iter_users()?;
+
+        if let Some(user) = users.iter().find(|user| {
+            user.oauth_provider.as_deref() == Some(provider.as_str())
+                && user.oauth_subject.as_deref() == Some(external_id.as_str())
+        }) {
+            return Ok(user.user_id);
+        }
+
+        if email_verified {
+            if let Some(user) = users.into_iter().find(|user| user.email == email) {
+                let user_id = user.user_id;
+                let mut user = user;
+                user.oauth_provider = Some(provider);
+                user.oauth_subject = Some(external_id);
+                self.backend.put_user(&user.username.clone(), &user)?;
+                return Ok(user_id);
+            }
+        }
+
+        let username = format!("{provider}:{external_id}");
+        let signup_time = get_current_unix_time_seconds();
+        let user_id = self.backend.create_user(
+            &username,
+            Box::new(move |user_id| UserEntry {
+                username,
+                user_id,
+                email,
+                password_hash: None,
+                signup_time,
+                oauth_provider: Some(provider),
+                oauth_subject: Some(external_id),
+            }),
+        )?;
+        Ok(user_id)
+    }
+
+    /// Issues a session token for an already-resolved user, e.g. after a
+    /// successful OAuth2 callback.
+    pub fn new_session_for_user(
+        &self,
+        user_id: u64,
+        jwt_secret: &[u8],
+    ) -> Result<String, AndyError> {
+        self.issue_session_token(user_id, jwt_secret)
+    }
+
+    pub fn new_card_deck(&self, user_id: u64, deck_name: String) -> Result<u64, AndyError> {
+        let creation_time = get_current_unix_time_seconds();
+        self.backend.create_deck(
+            user_id,
+            Box::new(move |_deck_id| CardDeck {
+                creation_time,
+                cards: vec![],
+                name: deck_name,
+            }),
+        )
     }
 
     pub fn new_card(
@@ -106,40 +240,33 @@ impl Database {
         question: String,
         answer: String,
     ) -> Result<(), AndyError> {
-        let key = (user_id, deck_id);
-
-        let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(Self::DECKS_TABLE)?;
-        let mut deck = table.get(key)?.unwrap().value();
+        let mut deck = self
+            .backend
+            .get_deck(user_id, deck_id)?
+            .ok_or(AndyError::UserDoesNotExist)?;
         deck.cards.push(Card { question, answer });
 
-        self.insert(key, deck, Self::DECKS_TABLE)?;
-        Ok(())
+        self.backend.put_deck(user_id, deck_id, &deck)
     }
 
     pub fn list_card_decks(
         &self,
         user_id: u64,
     ) -> Result<api_structs::ListCardDecksResponse, AndyError> {
-        let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(Self::DECKS_TABLE)?;
-
-        let mut deck_ids: Vec<api_structs::CardDeck> = vec![];
-
-        for entry in table.iter()? {
-            let entry = entry?;
-            let id_pair = entry.0.value();
-            if id_pair.0 == user_id {
-                let deck = entry.1.value();
-                deck_ids.push(api_structs::CardDeck {
-                    deck_id: id_pair.1,
+        let decks = self
+            .backend
+            .iter_decks_for_user(user_id)?
+            .into_iter()
+            .map(|(deck_id, deck)| {
+                Ok(api_structs::CardDeck {
+                    deck_id: public_id::encode(deck_id),
                     name: deck.name,
                     num_cards: deck.cards.len().try_into()?,
-                });
-            }
-        }
+                })
+            })
+            .collect::<Result<Vec<_>, AndyError>>()?;
 
-        Ok(api_structs::ListCardDecksResponse { decks: deck_ids })
+        Ok(api_structs::ListCardDecksResponse { decks })
     }
 
     pub fn list_cards(
@@ -147,11 +274,10 @@ impl Database {
         user_id: u64,
         deck_id: u64,
     ) -> Result<api_structs::ListCardsResponse, AndyError> {
-        let key = (user_id, deck_id);
-
-        let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(Self::DECKS_TABLE)?;
-        let deck = table.get(key)?.unwrap().value();
+        let deck = self
+            .backend
+            .get_deck(user_id, deck_id)?
+            .ok_or(AndyError::UserDoesNotExist)?;
 
         Ok(api_structs::ListCardsResponse {
             cards: deck
@@ -164,45 +290,30 @@ impl Database {
                 .collect(),
         })
     }
-
-    fn insert<'a, K, V>(
-        &self,
-        key: K,
-        val: V,
-        table: redb::TableDefinition<'static, K, V>,
-    ) -> Result<(), AndyError>
-    where
-        K: redb::RedbKey + core::borrow::Borrow<<K as redb::RedbValue>::SelfType<'a>>,
-        V: redb::RedbValue + core::borrow::Borrow<<V as redb::RedbValue>::SelfType<'a>>,
-    {
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(table)?;
-            table.insert(key, val)?;
-        }
-        write_txn.commit()?;
-
-        Ok(())
-    }
 }
 
-fn sha256_hash(bytes: &[u8]) -> [u8; SHA265_NUM_BYTES] {
-    let mut hasher = sha2::Sha256::new();
-
-    hasher.update(bytes);
-
-    let result: Vec<u8> = hasher.finalize().to_vec();
+fn hash_password(password: &str) -> Result<String, AndyError> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("argon2 hashing with default params should not fail");
+    Ok(hash.to_string())
+}
 
-    return result.try_into().unwrap();
+fn verify_password(password_hash: Option<&str>, password: &str) -> Result<(), AndyError> {
+    let password_hash = password_hash.ok_or(AndyError::WrongPassword)?;
+    let parsed_hash = PasswordHash::new(password_hash).map_err(|_| AndyError::WrongPassword)?;
+    Argon2::default()
+        .verify_password(password.as_bytes(), &parsed_hash)
+        .map_err(|_| AndyError::WrongPassword)
 }
 
-fn hash<K>(username: K) -> u64
-where
-    K: std::hash::Hash,
-{
-    let mut hasher = std::collections::hash_map::DefaultHasher::new();
-    username.hash(&mut hasher);
-    hasher.finish()
+fn generate_state_nonce() -> String {
+    use rand_core::RngCore;
+
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
 }
 
 fn get_current_unix_time_seconds() -> u64 {
@@ -213,33 +324,100 @@ fn get_current_unix_time_seconds() -> u64 {
         .as_secs()
 }
 
-macro_rules! implement_redb_value {
-    ($typename:ty, $unique_identifier:expr) => {
-        impl redb::RedbValue for $typename {
-            type SelfType<'a> = Self;
-            type AsBytes<'a> = Vec<u8>;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::storage::MemoryBackend;
 
-            fn fixed_width() -> Option<usize> {
-                None
-            }
+    const JWT_SECRET: &[u8] = b"test-secret";
 
-            fn from_bytes<'a>(data: &'a [u8]) -> Self::SelfType<'a>
-            where
-                Self: 'a,
-            {
-                serde_json::from_slice(data).expect("database deserialization messed up")
-            }
+    fn test_db() -> Database {
+        Database::with_backend(Box::new(MemoryBackend::new()))
+    }
 
-            fn as_bytes<'a, 'b: 'a>(value: &Self) -> Vec<u8> {
-                serde_json::to_vec(value).expect("database serialization messed up")
-            }
+    fn user_id_of(db: &Database, email: &str) -> u64 {
+        db.backend
+            .iter_users()
+            .unwrap()
+            .into_iter()
+            .find(|user| user.email == email)
+            .unwrap()
+            .user_id
+    }
 
-            fn type_name() -> redb::TypeName {
-                redb::TypeName::new($unique_identifier)
-            }
-        }
-    };
-}
+    #[test]
+    fn new_session_round_trips_through_validate_token() {
+        let db = test_db();
+        db.new_user("alice".to_owned(), "alice@example.com".to_owned(), "hunter2".to_owned())
+            .unwrap();
+        let user_id = user_id_of(&db, "alice@example.com");
+
+        let token = db
+            .new_session(user_id, "hunter2".to_owned(), JWT_SECRET)
+            .unwrap();
+
+        assert_eq!(db.validate_token(token, JWT_SECRET).unwrap(), user_id);
+    }
+
+    #[test]
+    fn new_session_rejects_wrong_password() {
+        let db = test_db();
+        db.new_user("alice".to_owned(), "alice@example.com".to_owned(), "hunter2".to_owned())
+            .unwrap();
+        let user_id = user_id_of(&db, "alice@example.com");
+
+        let result = db.new_session(user_id, "wrong".to_owned(), JWT_SECRET);
+        assert!(matches!(result, Err(AndyError::WrongPassword)));
+    }
+
+    #[test]
+    fn change_password_invalidates_the_old_one() {
+        let db = test_db();
+        db.new_user("alice".to_owned(), "alice@example.com".to_owned(), "hunter2".to_owned())
+            .unwrap();
+        let user_id = user_id_of(&db, "alice@example.com");
+
+        db.change_password(
+            "alice@example.com".to_owned(),
+            "hunter2".to_owned(),
+            "hunter3".to_owned(),
+        )
+        .unwrap();
+
+        assert!(db
+            .new_session(user_id, "hunter2".to_owned(), JWT_SECRET)
+            .is_err());
+        assert!(db
+            .new_session(user_id, "hunter3".to_owned(), JWT_SECRET)
+            .is_ok());
+    }
 
-implement_redb_value!(CardDeck, "andy_card_deck");
-implement_redb_value!(UserEntry, "andy_user_entry");
+    #[test]
+    fn delete_user_removes_their_session_access() {
+        let db = test_db();
+        db.new_user("alice".to_owned(), "alice@example.com".to_owned(), "hunter2".to_owned())
+            .unwrap();
+        let user_id = user_id_of(&db, "alice@example.com");
+
+        db.delete_user("alice@example.com".to_owned(), "hunter2".to_owned())
+            .unwrap();
+
+        assert!(matches!(
+            db.new_session(user_id, "hunter2".to_owned(), JWT_SECRET),
+            Err(AndyError::UserDoesNotExist)
+        ));
+    }
+
+    #[test]
+    fn card_decks_get_distinct_ids_even_with_the_same_name() {
+        let db = test_db();
+        db.new_user("alice".to_owned(), "alice@example.com".to_owned(), "hunter2".to_owned())
+            .unwrap();
+        let user_id = user_id_of(&db, "alice@example.com");
+
+        let first = db.new_card_deck(user_id, "Capitals".to_owned()).unwrap();
+        let second = db.new_card_deck(user_id, "Capitals".to_owned()).unwrap();
+
+        assert_ne!(first, second);
+    }
+}
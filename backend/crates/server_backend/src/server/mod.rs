@@ -1,6 +1,10 @@
 pub mod database;
 pub mod llm;
+pub mod oauth;
+pub mod openapi;
+pub mod public_id;
 pub mod search_engine;
+pub mod storage;
 pub mod utils;
 
 use crate::api_structs;
@@ -16,6 +20,11 @@ pub struct SharedState {
     pub database: database::Database,
     pub llm_runner: llm::LlmRunner,
     pub search_engine: tokio::sync::Mutex<search_engine::SearchEngine>,
+    /// HMAC secret used to sign and verify session tokens, loaded once at startup.
+    pub jwt_secret: Vec<u8>,
+    /// OAuth2 "login with X" providers registered at startup, keyed by
+    /// provider name (e.g. "google").
+    pub oauth_providers: std::collections::HashMap<String, oauth::OAuthProviderConfig>,
 }
 
 pub async fn main_service(
@@ -40,6 +49,39 @@ pub async fn main_service(
     }
 }
 
+/// Single source of truth for every JSON endpoint this server serves except
+/// `GET /openapi.json` (which `handle_request` and `openapi::routes()` each
+/// add on their own): its method, path, handler, and the `api_structs`
+/// request/response type names `openapi::routes()` needs to describe it.
+/// `handle_request`'s dispatch and `openapi::routes()` both expand from this
+/// one list (via `dispatch_match!` and `build_routes!` respectively) instead
+/// of keeping separate copies in sync by hand. An empty schema name means the handler has no typed
+/// counterpart to document (e.g. it returns `()` or a bare `String`).
+macro_rules! route_table {
+    ($apply:ident) => {
+        $apply! {
+            (POST, api_structs::ENDPOINT_CREATE_CARD_DECK, create_card_deck, "CreateCardDeck", "CreateCardDeckResponse"),
+            (POST, api_structs::ENDPOINT_DELETE_CARD_DECK, delete_card_deck, "DeleteCardDeck", ""),
+            (POST, api_structs::ENDPOINT_CREATE_CARD, create_card, "CreateCard", ""),
+            (POST, api_structs::ENDPOINT_DELETE_CARD, delete_card, "DeleteCard", ""),
+            (POST, api_structs::ENDPOINT_NEW_USER, new_user, "NewUser", ""),
+            (POST, api_structs::ENDPOINT_LIST_CARD_DECKS, list_card_decks, "ListCardDecks", "ListCardDecksResponse"),
+            (POST, api_structs::ENDPOINT_LIST_CARDS, list_cards, "ListCards", "ListCardsResponse"),
+            (POST, api_structs::ENDPOINT_LOGIN, login, "LoginRequest", "LoginResponse"),
+            (POST, api_structs::ENDPOINT_CREATE_DECK_PDF, create_deck_pdf, "UploadPdf", "UploadPdfResponse"),
+            (POST, api_structs::ENDPOINT_DELETE_USER, delete_user, "DeleteUser", ""),
+            (POST, api_structs::ENDPOINT_CHANGE_PASSWORD, change_password, "ChangePassword", ""),
+            (POST, api_structs::ENDPOINT_AI_TEST, ai_test, "AiPromptTest", ""),
+            (POST, api_structs::ENDPOINT_GET_DECK, get_deck, "GetDeckRequest", "GetDeckResponse"),
+            (POST, api_structs::ENDPOINT_SEARCH_DECKS, search, "SearchDecksRequest", "SearchDecksResponse"),
+            (POST, api_structs::ENDPOINT_EDIT_CARD, edit_card, "EditCard", ""),
+            (POST, api_structs::ENDPOINT_OAUTH_START, oauth_start, "OAuthStartRequest", "OAuthStartResponse"),
+            (POST, api_structs::ENDPOINT_OAUTH_CALLBACK, oauth_callback, "OAuthCallbackRequest", "LoginResponse")
+        }
+    };
+}
+pub(crate) use route_table;
+
 async fn handle_request(
     req: Request<hyper::body::Incoming>,
     state: std::sync::Arc<SharedState>,
@@ -47,10 +89,36 @@ async fn handle_request(
     let uri = req.uri().path();
     let method = req.method();
     println!("got request method = {}, endpoint = {}", method, uri);
-    macro_rules! endpoints {
-        ($(($meth:pat, $uri:pat, $func:expr)),*) => {
+
+    if uri == "/openapi.json" {
+        return match method {
+            &hyper::Method::GET => utils::make_response(
+                hyper::StatusCode::OK,
+                vec![
+                    (hyper::header::CONTENT_TYPE, "application/json"),
+                    (hyper::header::ACCESS_CONTROL_ALLOW_ORIGIN, "*"),
+                ],
+                openapi::spec_json()?,
+            ),
+            &hyper::Method::OPTIONS => utils::cors_preflight_headers(req, vec!["GET"]),
+            method => {
+                println!("404 REQUEST: endpoint = /openapi.json, method = {}", method);
+                utils::make_response(
+                    hyper::StatusCode::NOT_FOUND,
+                    vec![
+                        (hyper::header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+                        (hyper::header::ACCESS_CONTROL_ALLOW_ORIGIN, "*"),
+                    ],
+                    "NOT FOUND".to_owned(),
+                )
+            }
+        };
+    }
+
+    macro_rules! dispatch_match {
+        ($(($meth:ident, $uri:expr, $func:ident, $req_schema:expr, $resp_schema:expr)),* $(,)?) => {
             match (method, uri) {
-                $((&$meth, $uri) => {
+                $((&hyper::Method::$meth, $uri) => {
                     let bytes = req.collect().await?.to_bytes();
                     let thing = serde_json::from_reader(bytes.reader())?;
                     let body_struct = $func(thing, state).await?;
@@ -62,9 +130,12 @@ async fn handle_request(
                         body_str
                     )
                 },)*
-                (&hyper::Method::OPTIONS, _) => {
-                    //TODO this assumes every endpoint is a POST request in CORS headers
-                    utils::cors_preflight_headers(req, vec!("POST"))
+                (&hyper::Method::OPTIONS, path) => {
+                    let methods: Vec<&str> = openapi::allowed_methods(path)
+                        .iter()
+                        .map(hyper::Method::as_str)
+                        .collect();
+                    utils::cors_preflight_headers(req, methods)
                 },
                 (method, endpoint) => {
                     println!("404 REQUEST: endpoint = {}, method = {}", endpoint, method);
@@ -79,75 +150,49 @@ async fn handle_request(
         }
     }
 
-    endpoints!(
-        (
-            hyper::Method::POST,
-            api_structs::ENDPOINT_CREATE_CARD_DECK,
-            create_card_deck
-        ),
-        (
-            hyper::Method::POST,
-            api_structs::ENDPOINT_DELETE_CARD_DECK,
-            delete_card_deck
-        ),
-        (
-            hyper::Method::POST,
-            api_structs::ENDPOINT_CREATE_CARD,
-            create_card
-        ),
-        (
-            hyper::Method::POST,
-            api_structs::ENDPOINT_DELETE_CARD,
-            delete_card
-        ),
-        (
-            hyper::Method::POST,
-            api_structs::ENDPOINT_NEW_USER,
-            new_user
-        ),
-        (
-            hyper::Method::POST,
-            api_structs::ENDPOINT_LIST_CARD_DECKS,
-            list_card_decks
-        ),
-        (
-            hyper::Method::POST,
-            api_structs::ENDPOINT_LIST_CARDS,
-            list_cards
-        ),
-        (hyper::Method::POST, api_structs::ENDPOINT_LOGIN, login),
-        (
-            hyper::Method::POST,
-            api_structs::ENDPOINT_CREATE_DECK_PDF,
-            create_deck_pdf
-        ),
-        (
-            hyper::Method::POST,
-            api_structs::ENDPOINT_DELETE_USER,
-            delete_user
-        ),
-        (
-            hyper::Method::POST,
-            api_structs::ENDPOINT_CHANGE_PASSWORD,
-            change_password
-        ),
-        (hyper::Method::POST, api_structs::ENDPOINT_AI_TEST, ai_test),
-        (
-            hyper::Method::POST,
-            api_structs::ENDPOINT_GET_DECK,
-            get_deck
-        ),
-        (
-            hyper::Method::POST,
-            api_structs::ENDPOINT_SEARCH_DECKS,
-            search
-        ),
-        (
-            hyper::Method::POST,
-            api_structs::ENDPOINT_EDIT_CARD,
-            edit_card
-        )
-    )
+    route_table!(dispatch_match)
+}
+
+async fn oauth_start(
+    info: api_structs::OAuthStartRequest,
+    state: std::sync::Arc<SharedState>,
+) -> Result<api_structs::OAuthStartResponse, AndyError> {
+    let config = state
+        .oauth_providers
+        .get(&info.provider)
+        .ok_or(AndyError::OAuthProviderNotConfigured)?;
+
+    let oauth_state = state.database.begin_oauth_state(info.provider)?;
+    let authorize_url = oauth::authorize_url(config, &oauth_state);
+
+    Ok(api_structs::OAuthStartResponse { authorize_url })
+}
+
+async fn oauth_callback(
+    info: api_structs::OAuthCallbackRequest,
+    state: std::sync::Arc<SharedState>,
+) -> Result<api_structs::LoginResponse, AndyError> {
+    let provider = state.database.consume_oauth_state(info.state)?;
+    let config = state
+        .oauth_providers
+        .get(&provider)
+        .ok_or(AndyError::OAuthProviderNotConfigured)?;
+
+    let userinfo = oauth::exchange_code_for_userinfo(config, &info.code).await?;
+    let user_id = state.database.oauth_login(
+        provider,
+        userinfo.subject,
+        userinfo.email,
+        userinfo.email_verified,
+    )?;
+    let access_token = state
+        .database
+        .new_session_for_user(user_id, &state.jwt_secret)?;
+
+    Ok(api_structs::LoginResponse {
+        access_token,
+        user_id: public_id::encode(user_id),
+    })
 }
 
 async fn login(
@@ -155,27 +200,36 @@ async fn login(
     state: std::sync::Arc<SharedState>,
 ) -> Result<api_structs::LoginResponse, AndyError> {
     let user_id = state.database.get_user_id(&info.email);
-    let access_token = state.database.new_session(user_id, info.password)?;
+    let access_token =
+        state
+            .database
+            .new_session(user_id, info.password, &state.jwt_secret)?;
     Ok(api_structs::LoginResponse {
         access_token,
-        user_id,
+        user_id: public_id::encode(user_id),
     })
 }
 
 async fn create_card_deck(
     info: api_structs::CreateCardDeck,
     state: std::sync::Arc<SharedState>,
-) -> Result<(), AndyError> {
-    let user_id = state.database.validate_token(info.access_token)?;
-    state.database.new_card_deck(user_id, info.deck_name)?;
-    Ok(())
+) -> Result<api_structs::CreateCardDeckResponse, AndyError> {
+    let user_id = state
+        .database
+        .validate_token(info.access_token, &state.jwt_secret)?;
+    let deck_id = state.database.new_card_deck(user_id, info.deck_name)?;
+    Ok(api_structs::CreateCardDeckResponse {
+        deck_id: public_id::encode(deck_id),
+    })
 }
 
 async fn get_deck(
     info: api_structs::GetDeckRequest,
     state: std::sync::Arc<SharedState>,
 ) -> Result<api_structs::GetDeckResponse, AndyError> {
-    let name = state.database.get_deck_info(info.user_id, info.deck_id)?;
+    let user_id = public_id::decode(&info.user_id)?;
+    let deck_id = public_id::decode(&info.deck_id)?;
+    let name = state.database.get_deck_info(user_id, deck_id)?;
     Ok(name)
 }
 
@@ -183,8 +237,11 @@ async fn delete_card_deck(
     info: api_structs::DeleteCardDeck,
     state: std::sync::Arc<SharedState>,
 ) -> Result<(), AndyError> {
-    let user_id = state.database.validate_token(info.access_token)?;
-    state.database.delete_card_deck(user_id, info.deck_id)?;
+    let user_id = state
+        .database
+        .validate_token(info.access_token, &state.jwt_secret)?;
+    let deck_id = public_id::decode(&info.deck_id)?;
+    state.database.delete_card_deck(user_id, deck_id)?;
     Ok(())
 }
 
@@ -192,10 +249,13 @@ async fn create_card(
     info: api_structs::CreateCard,
     state: std::sync::Arc<SharedState>,
 ) -> Result<(), AndyError> {
-    let user_id = state.database.validate_token(info.access_token)?;
+    let user_id = state
+        .database
+        .validate_token(info.access_token, &state.jwt_secret)?;
+    let deck_id = public_id::decode(&info.deck_id)?;
     state
         .database
-        .new_card(user_id, info.deck_id, info.question, info.answer)?;
+        .new_card(user_id, deck_id, info.question, info.answer)?;
     Ok(())
 }
 
@@ -203,10 +263,13 @@ async fn delete_card(
     info: api_structs::DeleteCard,
     state: std::sync::Arc<SharedState>,
 ) -> Result<(), AndyError> {
-    let user_id = state.database.validate_token(info.access_token)?;
+    let user_id = state
+        .database
+        .validate_token(info.access_token, &state.jwt_secret)?;
+    let deck_id = public_id::decode(&info.deck_id)?;
     state
         .database
-        .delete_card(user_id, info.deck_id, info.card_index)?;
+        .delete_card(user_id, deck_id, info.card_index)?;
     Ok(())
 }
 
@@ -214,10 +277,13 @@ async fn edit_card(
     info: api_structs::EditCard,
     state: std::sync::Arc<SharedState>,
 ) -> Result<(), AndyError> {
-    let user_id = state.database.validate_token(info.access_token)?;
+    let user_id = state
+        .database
+        .validate_token(info.access_token, &state.jwt_secret)?;
+    let deck_id = public_id::decode(&info.deck_id)?;
     state.database.edit_card(
         user_id,
-        info.deck_id,
+        deck_id,
         info.card_index,
         info.new_question,
         info.new_answer,
@@ -257,7 +323,9 @@ async fn list_card_decks(
     info: api_structs::ListCardDecks,
     state: std::sync::Arc<SharedState>,
 ) -> Result<api_structs::ListCardDecksResponse, AndyError> {
-    let user_id = state.database.validate_token(info.access_token)?;
+    let user_id = state
+        .database
+        .validate_token(info.access_token, &state.jwt_secret)?;
     state.database.list_card_decks(user_id)
 }
 
@@ -265,7 +333,9 @@ async fn list_cards(
     info: api_structs::ListCards,
     state: std::sync::Arc<SharedState>,
 ) -> Result<api_structs::ListCardsResponse, AndyError> {
-    state.database.list_cards(info.user_id, info.deck_id)
+    let user_id = public_id::decode(&info.user_id)?;
+    let deck_id = public_id::decode(&info.deck_id)?;
+    state.database.list_cards(user_id, deck_id)
 }
 
 async fn search(
@@ -282,18 +352,117 @@ async fn search(
     Ok(api_structs::SearchDecksResponse { decks: thing })
 }
 
+/// Target size (in characters) for each chunk of PDF text sent to the model.
+const PDF_CHUNK_CHARS: usize = 6000;
+/// How much consecutive chunks overlap, so flashcards near a chunk boundary
+/// still have enough surrounding context.
+const PDF_CHUNK_OVERLAP_CHARS: usize = 500;
+
+#[derive(serde::Deserialize)]
+struct GeneratedCard {
+    question: String,
+    answer: String,
+}
+
+fn chunk_pdf_text(text: &str, chunk_size: usize, overlap: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() {
+        return vec![];
+    }
+
+    let mut chunks = vec![];
+    let mut start = 0;
+    while start < chars.len() {
+        let end = (start + chunk_size).min(chars.len());
+        chunks.push(chars[start..end].iter().collect());
+        if end == chars.len() {
+            break;
+        }
+        start = end.saturating_sub(overlap);
+    }
+    chunks
+}
+
+fn flashcard_prompt(chunk: &str, strict: bool) -> String {
+    let instruction = if strict {
+        "Respond with ONLY a JSON array and nothing else - no prose, no markdown fences. \
+         Each element must be an object with exactly the string keys \"question\" and \"answer\"."
+    } else {
+        "Read the study material below and turn it into flashcards. \
+         Respond with a JSON array of objects, each with a \"question\" and \"answer\" string field."
+    };
+
+    format!("{instruction}\n\nMaterial:\n{chunk}")
+}
+
+async fn generate_cards_for_chunk(state: &SharedState, chunk: &str) -> Vec<GeneratedCard> {
+    let Ok(response) = state
+        .llm_runner
+        .submit_prompt(flashcard_prompt(chunk, false))
+        .await
+    else {
+        return vec![];
+    };
+
+    if let Ok(cards) = serde_json::from_str::<Vec<GeneratedCard>>(response.trim()) {
+        return cards;
+    }
+
+    // The model didn't return strict JSON - ask once more, more forcefully,
+    // before giving up on this chunk.
+    let Ok(retry_response) = state
+        .llm_runner
+        .submit_prompt(flashcard_prompt(chunk, true))
+        .await
+    else {
+        return vec![];
+    };
+
+    serde_json::from_str::<Vec<GeneratedCard>>(retry_response.trim()).unwrap_or_default()
+}
+
 async fn create_deck_pdf(
     info: api_structs::UploadPdf,
     state: std::sync::Arc<SharedState>,
-) -> Result<(), AndyError> {
-    let _user_id = state.database.validate_token(info.access_token)?;
+) -> Result<api_structs::UploadPdfResponse, AndyError> {
+    let user_id = state
+        .database
+        .validate_token(info.access_token, &state.jwt_secret)?;
 
     let url = data_url::DataUrl::process(&info.file_bytes_base64).unwrap();
     let (body, _fragment) = url.decode_to_vec().unwrap();
 
-    let _lines = pdf_parser::extract_text(&body)?;
+    let lines = pdf_parser::extract_text(&body)?;
+    let text = lines.join("\n");
+
+    let deck_name = info.deck_name.unwrap_or_else(|| {
+        lines
+            .iter()
+            .map(|line| line.trim())
+            .find(|line| !line.is_empty())
+            .map(|line| line.to_owned())
+            .unwrap_or_else(|| "Untitled deck".to_owned())
+    });
+    let deck_id = state.database.new_card_deck(user_id, deck_name)?;
 
-    todo!()
+    let mut seen = std::collections::HashSet::new();
+    let mut card_count: u32 = 0;
+    for chunk in chunk_pdf_text(&text, PDF_CHUNK_CHARS, PDF_CHUNK_OVERLAP_CHARS) {
+        for card in generate_cards_for_chunk(&state, &chunk).await {
+            if !seen.insert((card.question.clone(), card.answer.clone())) {
+                continue;
+            }
+            state
+                .database
+                .new_card(user_id, deck_id, card.question, card.answer)?;
+            card_count += 1;
+        }
+    }
+
+    Ok(api_structs::UploadPdfResponse {
+        deck_id: public_id::encode(deck_id),
+        card_count,
+    })
 }
 
 async fn ai_test(
@@ -304,3 +473,25 @@ async fn ai_test(
 
     Ok(ai_response)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_pdf_text_splits_long_text_with_overlap() {
+        let chunks = chunk_pdf_text("0123456789abcdefgh", 10, 2);
+
+        assert_eq!(chunks, vec!["0123456789".to_owned(), "89abcdefgh".to_owned()]);
+    }
+
+    #[test]
+    fn chunk_pdf_text_returns_nothing_for_empty_input() {
+        assert!(chunk_pdf_text("", 10, 3).is_empty());
+    }
+
+    #[test]
+    fn chunk_pdf_text_returns_a_single_chunk_when_shorter_than_chunk_size() {
+        assert_eq!(chunk_pdf_text("short", 10, 3), vec!["short".to_owned()]);
+    }
+}
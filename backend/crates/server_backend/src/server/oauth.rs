@@ -0,0 +1,92 @@
+use crate::AndyError;
+
+/// Configuration for one OAuth2 "login with X" provider. Multiple providers
+/// can be registered by keying `SharedState::oauth_providers` on a provider
+/// name (e.g. "google", "github").
+#[derive(Clone)]
+pub struct OAuthProviderConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub authorize_url: String,
+    pub token_url: String,
+    pub userinfo_url: String,
+    pub redirect_uri: String,
+}
+
+#[derive(serde::Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(serde::Deserialize)]
+struct RawUserInfo {
+    sub: String,
+    email: String,
+    #[serde(default)]
+    email_verified: bool,
+}
+
+pub struct OAuthUserInfo {
+    pub subject: String,
+    pub email: String,
+    /// Whether the provider asserts it has verified ownership of `email`.
+    /// Only a verified email may be used to link to an existing account -
+    /// see `Database::oauth_login`.
+    pub email_verified: bool,
+}
+
+/// Builds the URL the client should redirect the user to in order to log in
+/// with `config`'s provider, embedding the previously-minted anti-CSRF
+/// `state` nonce.
+pub fn authorize_url(config: &OAuthProviderConfig, state: &str) -> String {
+    reqwest::Url::parse_with_params(
+        &config.authorize_url,
+        &[
+            ("response_type", "code"),
+            ("client_id", config.client_id.as_str()),
+            ("redirect_uri", config.redirect_uri.as_str()),
+            ("state", state),
+        ],
+    )
+    .expect("authorize_url must be a valid base URL")
+    .to_string()
+}
+
+/// Exchanges the authorization `code` the provider redirected back with for
+/// an access token, then fetches the userinfo it describes.
+pub async fn exchange_code_for_userinfo(
+    config: &OAuthProviderConfig,
+    code: &str,
+) -> Result<OAuthUserInfo, AndyError> {
+    let client = reqwest::Client::new();
+
+    let token_response: TokenResponse = client
+        .post(&config.token_url)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", config.client_secret.as_str()),
+            ("redirect_uri", config.redirect_uri.as_str()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let raw: RawUserInfo = client
+        .get(&config.userinfo_url)
+        .bearer_auth(token_response.access_token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(OAuthUserInfo {
+        subject: raw.sub,
+        email: raw.email,
+        email_verified: raw.email_verified,
+    })
+}
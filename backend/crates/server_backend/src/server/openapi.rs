@@ -0,0 +1,175 @@
+use crate::api_structs;
+use crate::server::route_table;
+use crate::AndyError;
+use std::sync::OnceLock;
+use utoipa::openapi::path::{OperationBuilder, PathItemBuilder};
+use utoipa::openapi::request_body::RequestBodyBuilder;
+use utoipa::openapi::{
+    ComponentsBuilder, ContentBuilder, InfoBuilder, ObjectBuilder, OpenApi, OpenApiBuilder,
+    PathsBuilder, Ref, ResponseBuilder, ResponsesBuilder,
+};
+
+const API_VERSION: &str = "0.1.0";
+
+/// One entry in the route table, shared by both the `GET /openapi.json`
+/// document and the `Allow` header a CORS preflight advertises for a given
+/// path - so those two never drift from each other. Built from
+/// `route_table!`, the same macro `handle_request`'s dispatch expands from,
+/// so there's exactly one hand-maintained list of endpoints in the crate;
+/// only the `GET /openapi.json` entry is appended here by hand.
+pub struct RouteInfo {
+    pub method: hyper::Method,
+    pub path: &'static str,
+    pub operation_id: &'static str,
+    /// `api_structs` schema name for the request body, if this route has one.
+    pub request_schema: Option<&'static str>,
+    /// `api_structs` schema name for the 200 response, if this route has one.
+    pub response_schema: Option<&'static str>,
+}
+
+fn non_empty(name: &'static str) -> Option<&'static str> {
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+pub fn routes() -> &'static [RouteInfo] {
+    static ROUTES: OnceLock<Vec<RouteInfo>> = OnceLock::new();
+    ROUTES.get_or_init(|| {
+        macro_rules! build_routes {
+            ($(($meth:ident, $uri:expr, $func:ident, $req_schema:expr, $resp_schema:expr)),* $(,)?) => {
+                vec![
+                    $(RouteInfo {
+                        method: hyper::Method::$meth,
+                        path: $uri,
+                        operation_id: stringify!($func),
+                        request_schema: non_empty($req_schema),
+                        response_schema: non_empty($resp_schema),
+                    },)*
+                    RouteInfo {
+                        method: hyper::Method::GET,
+                        path: "/openapi.json",
+                        operation_id: "openapi_json",
+                        request_schema: None,
+                        response_schema: None,
+                    },
+                ]
+            };
+        }
+
+        route_table!(build_routes)
+    })
+}
+
+/// The HTTP methods actually registered for `path`, used to answer a CORS
+/// preflight truthfully instead of assuming every route is a `POST`.
+pub fn allowed_methods(path: &str) -> Vec<hyper::Method> {
+    routes()
+        .iter()
+        .filter(|route| route.path == path)
+        .map(|route| route.method.clone())
+        .collect()
+}
+
+/// Content referencing the named component schema, for a route with a real
+/// `api_structs` type to document.
+fn schema_ref_content(schema_name: &str) -> utoipa::openapi::Content {
+    ContentBuilder::new()
+        .schema(Ref::from_schema_name(schema_name))
+        .build()
+}
+
+/// Content for a route with no typed request/response body to point at (e.g.
+/// it returns `()` or a bare `String`).
+fn untyped_content() -> utoipa::openapi::Content {
+    ContentBuilder::new().schema(ObjectBuilder::new()).build()
+}
+
+fn operation_for(route: &RouteInfo) -> utoipa::openapi::path::Operation {
+    let response_content = match route.response_schema {
+        Some(name) => schema_ref_content(name),
+        None => untyped_content(),
+    };
+
+    let mut builder = OperationBuilder::new()
+        .operation_id(Some(route.operation_id.to_owned()))
+        .responses(
+            ResponsesBuilder::new()
+                .response(
+                    "200",
+                    ResponseBuilder::new()
+                        .description("Success")
+                        .content("application/json", response_content)
+                        .build(),
+                )
+                .build(),
+        );
+
+    if route.method != hyper::Method::GET {
+        let request_content = match route.request_schema {
+            Some(name) => schema_ref_content(name),
+            None => untyped_content(),
+        };
+        builder = builder.request_body(Some(
+            RequestBodyBuilder::new()
+                .content("application/json", request_content)
+                .build(),
+        ));
+    }
+
+    builder.build()
+}
+
+/// Renders the aggregated OpenAPI document served at `GET /openapi.json`.
+pub fn spec_json() -> Result<String, AndyError> {
+    static SPEC: OnceLock<OpenApi> = OnceLock::new();
+    let spec = SPEC.get_or_init(|| {
+        let mut paths = PathsBuilder::new();
+        for route in routes() {
+            let item = match route.method {
+                hyper::Method::GET => PathItemBuilder::new().get(operation_for(route)),
+                _ => PathItemBuilder::new().post(operation_for(route)),
+            }
+            .build();
+            paths = paths.path(route.path, item);
+        }
+
+        OpenApiBuilder::new()
+            .info(InfoBuilder::new().title("Cognoso API").version(API_VERSION).build())
+            .paths(paths.build())
+            .components(Some(
+                api_structs::register_schemas(ComponentsBuilder::new()).build(),
+            ))
+            .build()
+    });
+
+    Ok(serde_json::to_string(spec)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `routes()` and `handle_request`'s dispatch both expand from the same
+    /// `route_table!` list, so they can't drift from each other the way two
+    /// hand-copied lists could - this just pins down the total as a sanity
+    /// check on that claim.
+    #[test]
+    fn routes_cover_every_endpoint_in_the_route_table() {
+        assert_eq!(routes().len(), 18);
+    }
+
+    #[test]
+    fn typed_routes_have_a_non_empty_schema_name() {
+        for route in routes() {
+            if let Some(name) = route.request_schema {
+                assert!(!name.is_empty());
+            }
+            if let Some(name) = route.response_schema {
+                assert!(!name.is_empty());
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use crate::AndyError;
+use std::sync::OnceLock;
+
+/// Encodes/decodes the internal `u64` ids used as table keys into short,
+/// non-enumerable ids safe to hand back to clients. Every id that crosses the
+/// API boundary (user ids, deck ids, ...) should go through here rather than
+/// being serialized raw.
+fn sqids() -> &'static sqids::Sqids {
+    static SQIDS: OnceLock<sqids::Sqids> = OnceLock::new();
+    SQIDS.get_or_init(|| {
+        sqids::Sqids::builder()
+            .alphabet(
+                "XC8mFjzU5pQ2K6bYT9sNcLHd4RgJ3aVfWrMq7nE1hBtPx0ZyoSiuD"
+                    .chars()
+                    .collect(),
+            )
+            .min_length(8)
+            .build()
+            .expect("hardcoded sqids alphabet/min_length are valid")
+    })
+}
+
+pub fn encode(id: u64) -> String {
+    sqids()
+        .encode(&[id])
+        .expect("a single u64 always fits the configured alphabet")
+}
+
+pub fn decode(public_id: &str) -> Result<u64, AndyError> {
+    match sqids().decode(public_id).as_slice() {
+        [id] => Ok(*id),
+        _ => Err(AndyError::InvalidPublicId),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_then_decode_round_trips() {
+        for id in [0, 1, 42, u64::MAX] {
+            assert_eq!(decode(&encode(id)).unwrap(), id);
+        }
+    }
+
+    #[test]
+    fn decode_rejects_garbage() {
+        assert!(matches!(decode("not a real id"), Err(AndyError::InvalidPublicId)));
+    }
+}
@@ -0,0 +1,125 @@
+use super::{CardDeck, StorageBackend, UserEntry};
+use crate::AndyError;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// In-memory backend for tests - no file, no redb, just a few `BTreeMap`s,
+/// each behind its own mutex.
+#[derive(Default)]
+pub struct MemoryBackend {
+    users: Mutex<BTreeMap<String, UserEntry>>,
+    decks: Mutex<BTreeMap<(u64, u64), CardDeck>>,
+    oauth_states: Mutex<BTreeMap<String, (String, u64)>>,
+    counters: Mutex<BTreeMap<String, u64>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MemoryBackend {
+    /// Allocates the next value of `counter`, starting at 1.
+    fn next_id(&self, counter: &str) -> u64 {
+        let mut counters = self.counters.lock().unwrap();
+        let next = counters.entry(counter.to_owned()).or_insert(0);
+        *next += 1;
+        *next
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn create_user(
+        &self,
+        username: &str,
+        make_user: Box<dyn FnOnce(u64) -> UserEntry>,
+    ) -> Result<u64, AndyError> {
+        let user_id = self.next_id("users");
+        self.users
+            .lock()
+            .unwrap()
+            .insert(username.to_owned(), make_user(user_id));
+        Ok(user_id)
+    }
+
+    fn put_user(&self, username: &str, user: &UserEntry) -> Result<(), AndyError> {
+        self.users
+            .lock()
+            .unwrap()
+            .insert(username.to_owned(), user.clone());
+        Ok(())
+    }
+
+    fn iter_users(&self) -> Result<Vec<UserEntry>, AndyError> {
+        Ok(self.users.lock().unwrap().values().cloned().collect())
+    }
+
+    fn delete_user(&self, username: &str) -> Result<(), AndyError> {
+        self.users.lock().unwrap().remove(username);
+        Ok(())
+    }
+
+    fn create_deck(
+        &self,
+        user_id: u64,
+        make_deck: Box<dyn FnOnce(u64) -> CardDeck>,
+    ) -> Result<u64, AndyError> {
+        let deck_id = self.next_id("decks");
+        self.decks
+            .lock()
+            .unwrap()
+            .insert((user_id, deck_id), make_deck(deck_id));
+        Ok(deck_id)
+    }
+
+    fn put_deck(&self, user_id: u64, deck_id: u64, deck: &CardDeck) -> Result<(), AndyError> {
+        self.decks
+            .lock()
+            .unwrap()
+            .insert((user_id, deck_id), deck.clone());
+        Ok(())
+    }
+
+    fn get_deck(&self, user_id: u64, deck_id: u64) -> Result<Option<CardDeck>, AndyError> {
+        Ok(self
+            .decks
+            .lock()
+            .unwrap()
+            .get(&(user_id, deck_id))
+            .cloned())
+    }
+
+    fn iter_decks_for_user(&self, user_id: u64) -> Result<Vec<(u64, CardDeck)>, AndyError> {
+        Ok(self
+            .decks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|((owner, _), _)| *owner == user_id)
+            .map(|((_, deck_id), deck)| (*deck_id, deck.clone()))
+            .collect())
+    }
+
+    fn delete_deck(&self, user_id: u64, deck_id: u64) -> Result<(), AndyError> {
+        self.decks.lock().unwrap().remove(&(user_id, deck_id));
+        Ok(())
+    }
+
+    fn put_oauth_state(
+        &self,
+        state: &str,
+        provider: &str,
+        created_at: u64,
+    ) -> Result<(), AndyError> {
+        self.oauth_states
+            .lock()
+            .unwrap()
+            .insert(state.to_owned(), (provider.to_owned(), created_at));
+        Ok(())
+    }
+
+    fn take_oauth_state(&self, state: &str) -> Result<Option<(String, u64)>, AndyError> {
+        Ok(self.oauth_states.lock().unwrap().remove(state))
+    }
+}
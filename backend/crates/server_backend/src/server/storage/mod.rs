@@ -0,0 +1,83 @@
+mod memory_backend;
+mod redb_backend;
+mod sqlite_backend;
+
+pub use memory_backend::MemoryBackend;
+pub use redb_backend::RedbBackend;
+pub use sqlite_backend::SqliteBackend;
+
+use crate::AndyError;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct UserEntry {
+    pub username: String,
+    pub user_id: u64,
+    pub email: String,
+    /// PHC-format string (algorithm, salt and hash all encoded together).
+    /// `None` for accounts provisioned through an OAuth provider with no
+    /// local password.
+    pub password_hash: Option<String>,
+    pub signup_time: u64,
+    /// Provider name (e.g. "google") this account last linked to, if any.
+    pub oauth_provider: Option<String>,
+    /// The provider's stable subject id for this user, if linked.
+    pub oauth_subject: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct CardDeck {
+    pub creation_time: u64,
+    pub cards: Vec<Card>,
+    pub name: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct Card {
+    pub question: String,
+    pub answer: String,
+}
+
+/// Everything `Database` needs from whatever is actually persisting the data.
+///
+/// Swapping the backend (redb on disk, an in-memory map for tests, sqlite, ...)
+/// should never require touching the endpoint logic in `Database`.
+pub trait StorageBackend: Send + Sync {
+    /// Allocates a fresh "users" counter value and inserts the `UserEntry`
+    /// `make_user` builds from it, both inside the same write transaction -
+    /// so the id can never be handed out without the row that claims it
+    /// actually landing, or vice versa. Returns the allocated `user_id`.
+    fn create_user(
+        &self,
+        username: &str,
+        make_user: Box<dyn FnOnce(u64) -> UserEntry>,
+    ) -> Result<u64, AndyError>;
+    /// Overwrites an existing user row in place (e.g. changing its password
+    /// hash or linking an OAuth provider). Never allocates a new id.
+    fn put_user(&self, username: &str, user: &UserEntry) -> Result<(), AndyError>;
+    fn iter_users(&self) -> Result<Vec<UserEntry>, AndyError>;
+    fn delete_user(&self, username: &str) -> Result<(), AndyError>;
+
+    /// Allocates a fresh "decks" counter value and inserts the `CardDeck`
+    /// `make_deck` builds from it, both inside the same write transaction.
+    /// Returns the allocated `deck_id`.
+    fn create_deck(
+        &self,
+        user_id: u64,
+        make_deck: Box<dyn FnOnce(u64) -> CardDeck>,
+    ) -> Result<u64, AndyError>;
+    /// Overwrites an existing deck row in place (e.g. appending a card).
+    /// Never allocates a new id.
+    fn put_deck(&self, user_id: u64, deck_id: u64, deck: &CardDeck) -> Result<(), AndyError>;
+    fn get_deck(&self, user_id: u64, deck_id: u64) -> Result<Option<CardDeck>, AndyError>;
+    fn iter_decks_for_user(&self, user_id: u64) -> Result<Vec<(u64, CardDeck)>, AndyError>;
+    fn delete_deck(&self, user_id: u64, deck_id: u64) -> Result<(), AndyError>;
+
+    /// Stashes a short-lived OAuth2 `state` nonce alongside the provider it
+    /// was minted for and when, so the callback can validate it and enforce
+    /// a TTL.
+    fn put_oauth_state(&self, state: &str, provider: &str, created_at: u64)
+        -> Result<(), AndyError>;
+    /// Looks up and removes an OAuth2 state nonce (they're single-use),
+    /// returning the `(provider, created_at)` it was stored with.
+    fn take_oauth_state(&self, state: &str) -> Result<Option<(String, u64)>, AndyError>;
+}
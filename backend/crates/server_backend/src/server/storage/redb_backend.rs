@@ -0,0 +1,210 @@
+use super::{CardDeck, StorageBackend, UserEntry};
+use crate::AndyError;
+use redb::ReadableTable;
+
+pub struct RedbBackend {
+    db: redb::Database,
+}
+
+impl RedbBackend {
+    const USERS_TABLE: redb::TableDefinition<'static, &'static str, UserEntry> =
+        redb::TableDefinition::new("users");
+    const DECKS_TABLE: redb::TableDefinition<'static, (u64, u64), CardDeck> =
+        redb::TableDefinition::new("decks");
+    const OAUTH_STATES_TABLE: redb::TableDefinition<'static, &'static str, (String, u64)> =
+        redb::TableDefinition::new("oauth_states");
+    const COUNTERS_TABLE: redb::TableDefinition<'static, &'static str, u64> =
+        redb::TableDefinition::new("counters");
+
+    pub fn new(db_path: std::path::PathBuf) -> Result<Self, AndyError> {
+        let db = redb::Database::create(db_path)?;
+        {
+            //create tables
+            let write_txn = db.begin_write()?;
+            write_txn.open_table(Self::USERS_TABLE)?;
+            write_txn.open_table(Self::DECKS_TABLE)?;
+            write_txn.open_table(Self::OAUTH_STATES_TABLE)?;
+            write_txn.open_table(Self::COUNTERS_TABLE)?;
+            write_txn.commit()?;
+        }
+        Ok(Self { db })
+    }
+
+    fn insert<'a, K, V>(
+        &self,
+        key: K,
+        val: V,
+        table: redb::TableDefinition<'static, K, V>,
+    ) -> Result<(), AndyError>
+    where
+        K: redb::RedbKey + core::borrow::Borrow<<K as redb::RedbValue>::SelfType<'a>>,
+        V: redb::RedbValue + core::borrow::Borrow<<V as redb::RedbValue>::SelfType<'a>>,
+    {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(table)?;
+            table.insert(key, val)?;
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    /// Allocates the next value of `counter`, inside `write_txn`, starting at 1.
+    fn next_id_in_txn(
+        write_txn: &redb::WriteTransaction,
+        counter: &str,
+    ) -> Result<u64, AndyError> {
+        let mut table = write_txn.open_table(Self::COUNTERS_TABLE)?;
+        let next = table.get(counter)?.map(|entry| entry.value()).unwrap_or(0) + 1;
+        table.insert(counter, next)?;
+        Ok(next)
+    }
+}
+
+impl StorageBackend for RedbBackend {
+    fn create_user(
+        &self,
+        username: &str,
+        make_user: Box<dyn FnOnce(u64) -> UserEntry>,
+    ) -> Result<u64, AndyError> {
+        let write_txn = self.db.begin_write()?;
+        let user_id = Self::next_id_in_txn(&write_txn, "users")?;
+        {
+            let mut table = write_txn.open_table(Self::USERS_TABLE)?;
+            table.insert(username, make_user(user_id))?;
+        }
+        write_txn.commit()?;
+        Ok(user_id)
+    }
+
+    fn put_user(&self, username: &str, user: &UserEntry) -> Result<(), AndyError> {
+        self.insert(username, user.clone(), Self::USERS_TABLE)
+    }
+
+    fn iter_users(&self) -> Result<Vec<UserEntry>, AndyError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(Self::USERS_TABLE)?;
+
+        let mut users = vec![];
+        for entry in table.iter()? {
+            users.push(entry?.1.value());
+        }
+        Ok(users)
+    }
+
+    fn delete_user(&self, username: &str) -> Result<(), AndyError> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(Self::USERS_TABLE)?;
+            table.remove(username)?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    }
+
+    fn create_deck(
+        &self,
+        user_id: u64,
+        make_deck: Box<dyn FnOnce(u64) -> CardDeck>,
+    ) -> Result<u64, AndyError> {
+        let write_txn = self.db.begin_write()?;
+        let deck_id = Self::next_id_in_txn(&write_txn, "decks")?;
+        {
+            let mut table = write_txn.open_table(Self::DECKS_TABLE)?;
+            table.insert((user_id, deck_id), make_deck(deck_id))?;
+        }
+        write_txn.commit()?;
+        Ok(deck_id)
+    }
+
+    fn put_deck(&self, user_id: u64, deck_id: u64, deck: &CardDeck) -> Result<(), AndyError> {
+        self.insert((user_id, deck_id), deck.clone(), Self::DECKS_TABLE)
+    }
+
+    fn get_deck(&self, user_id: u64, deck_id: u64) -> Result<Option<CardDeck>, AndyError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(Self::DECKS_TABLE)?;
+        Ok(table.get((user_id, deck_id))?.map(|entry| entry.value()))
+    }
+
+    fn iter_decks_for_user(&self, user_id: u64) -> Result<Vec<(u64, CardDeck)>, AndyError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(Self::DECKS_TABLE)?;
+
+        let mut decks = vec![];
+        for entry in table.iter()? {
+            let entry = entry?;
+            let (owner, deck_id) = entry.0.value();
+            if owner == user_id {
+                decks.push((deck_id, entry.1.value()));
+            }
+        }
+        Ok(decks)
+    }
+
+    fn delete_deck(&self, user_id: u64, deck_id: u64) -> Result<(), AndyError> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(Self::DECKS_TABLE)?;
+            table.remove((user_id, deck_id))?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    }
+
+    fn put_oauth_state(
+        &self,
+        state: &str,
+        provider: &str,
+        created_at: u64,
+    ) -> Result<(), AndyError> {
+        self.insert(
+            state,
+            (provider.to_owned(), created_at),
+            Self::OAUTH_STATES_TABLE,
+        )
+    }
+
+    fn take_oauth_state(&self, state: &str) -> Result<Option<(String, u64)>, AndyError> {
+        let write_txn = self.db.begin_write()?;
+        let entry = {
+            let mut table = write_txn.open_table(Self::OAUTH_STATES_TABLE)?;
+            table.remove(state)?.map(|entry| entry.value())
+        };
+        write_txn.commit()?;
+        Ok(entry)
+    }
+}
+
+macro_rules! implement_redb_value {
+    ($typename:ty, $unique_identifier:expr) => {
+        impl redb::RedbValue for $typename {
+            type SelfType<'a> = Self;
+            type AsBytes<'a> = Vec<u8>;
+
+            fn fixed_width() -> Option<usize> {
+                None
+            }
+
+            fn from_bytes<'a>(data: &'a [u8]) -> Self::SelfType<'a>
+            where
+                Self: 'a,
+            {
+                serde_json::from_slice(data).expect("database deserialization messed up")
+            }
+
+            fn as_bytes<'a, 'b: 'a>(value: &Self) -> Vec<u8> {
+                serde_json::to_vec(value).expect("database serialization messed up")
+            }
+
+            fn type_name() -> redb::TypeName {
+                redb::TypeName::new($unique_identifier)
+            }
+        }
+    };
+}
+
+implement_redb_value!(CardDeck, "andy_card_deck");
+implement_redb_value!(UserEntry, "andy_user_entry");
+implement_redb_value!((String, u64), "andy_oauth_state");
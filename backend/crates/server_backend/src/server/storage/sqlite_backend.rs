@@ -0,0 +1,206 @@
+use super::{CardDeck, StorageBackend, UserEntry};
+use crate::AndyError;
+use std::sync::Mutex;
+
+/// Persistent backend backed by sqlite instead of redb, selectable at startup.
+///
+/// Rows store their payload as JSON, matching the encoding `RedbBackend` uses -
+/// this keeps `UserEntry`/`CardDeck` as the single source of truth for the shape
+/// of the data instead of mapping them onto a relational schema.
+pub struct SqliteBackend {
+    conn: Mutex<rusqlite::Connection>,
+}
+
+impl SqliteBackend {
+    pub fn new(db_path: std::path::PathBuf) -> Result<Self, AndyError> {
+        let conn = rusqlite::Connection::open(db_path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS users (username TEXT PRIMARY KEY, data TEXT NOT NULL)",
+            (),
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS decks (\
+                user_id INTEGER NOT NULL, \
+                deck_id INTEGER NOT NULL, \
+                data TEXT NOT NULL, \
+                PRIMARY KEY (user_id, deck_id)\
+            )",
+            (),
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS oauth_states (\
+                state TEXT PRIMARY KEY, \
+                provider TEXT NOT NULL, \
+                created_at INTEGER NOT NULL\
+            )",
+            (),
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS counters (name TEXT PRIMARY KEY, value INTEGER NOT NULL)",
+            (),
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Allocates the next value of `counter` on `conn`, starting at 1. The
+    /// caller is responsible for running this and the row insert it gates
+    /// inside the same transaction.
+    fn next_id_on(conn: &rusqlite::Connection, counter: &str) -> Result<u64, AndyError> {
+        conn.execute(
+            "INSERT INTO counters (name, value) VALUES (?1, 1) \
+             ON CONFLICT(name) DO UPDATE SET value = value + 1",
+            (counter,),
+        )?;
+        Ok(conn.query_row(
+            "SELECT value FROM counters WHERE name = ?1",
+            (counter,),
+            |row| row.get(0),
+        )?)
+    }
+}
+
+impl StorageBackend for SqliteBackend {
+    fn create_user(
+        &self,
+        username: &str,
+        make_user: Box<dyn FnOnce(u64) -> UserEntry>,
+    ) -> Result<u64, AndyError> {
+        let mut conn = self.conn.lock().unwrap();
+        let txn = conn.transaction()?;
+        let user_id = Self::next_id_on(&txn, "users")?;
+        let data = serde_json::to_string(&make_user(user_id))?;
+        txn.execute(
+            "INSERT OR REPLACE INTO users (username, data) VALUES (?1, ?2)",
+            (username, data),
+        )?;
+        txn.commit()?;
+        Ok(user_id)
+    }
+
+    fn put_user(&self, username: &str, user: &UserEntry) -> Result<(), AndyError> {
+        let data = serde_json::to_string(user)?;
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO users (username, data) VALUES (?1, ?2)",
+            (username, data),
+        )?;
+        Ok(())
+    }
+
+    fn iter_users(&self) -> Result<Vec<UserEntry>, AndyError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT data FROM users")?;
+        let rows = stmt.query_map((), |row| row.get::<_, String>(0))?;
+
+        let mut users = vec![];
+        for data in rows {
+            users.push(serde_json::from_str(&data?)?);
+        }
+        Ok(users)
+    }
+
+    fn delete_user(&self, username: &str) -> Result<(), AndyError> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM users WHERE username = ?1", (username,))?;
+        Ok(())
+    }
+
+    fn create_deck(
+        &self,
+        user_id: u64,
+        make_deck: Box<dyn FnOnce(u64) -> CardDeck>,
+    ) -> Result<u64, AndyError> {
+        let mut conn = self.conn.lock().unwrap();
+        let txn = conn.transaction()?;
+        let deck_id = Self::next_id_on(&txn, "decks")?;
+        let data = serde_json::to_string(&make_deck(deck_id))?;
+        txn.execute(
+            "INSERT OR REPLACE INTO decks (user_id, deck_id, data) VALUES (?1, ?2, ?3)",
+            (user_id, deck_id, data),
+        )?;
+        txn.commit()?;
+        Ok(deck_id)
+    }
+
+    fn put_deck(&self, user_id: u64, deck_id: u64, deck: &CardDeck) -> Result<(), AndyError> {
+        let data = serde_json::to_string(deck)?;
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO decks (user_id, deck_id, data) VALUES (?1, ?2, ?3)",
+            (user_id, deck_id, data),
+        )?;
+        Ok(())
+    }
+
+    fn get_deck(&self, user_id: u64, deck_id: u64) -> Result<Option<CardDeck>, AndyError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT data FROM decks WHERE user_id = ?1 AND deck_id = ?2")?;
+        let mut rows = stmt.query((user_id, deck_id))?;
+        match rows.next()? {
+            Some(row) => {
+                let data: String = row.get(0)?;
+                Ok(Some(serde_json::from_str(&data)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn iter_decks_for_user(&self, user_id: u64) -> Result<Vec<(u64, CardDeck)>, AndyError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT deck_id, data FROM decks WHERE user_id = ?1")?;
+        let rows = stmt.query_map((user_id,), |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut decks = vec![];
+        for row in rows {
+            let (deck_id, data) = row?;
+            decks.push((deck_id, serde_json::from_str(&data)?));
+        }
+        Ok(decks)
+    }
+
+    fn delete_deck(&self, user_id: u64, deck_id: u64) -> Result<(), AndyError> {
+        self.conn.lock().unwrap().execute(
+            "DELETE FROM decks WHERE user_id = ?1 AND deck_id = ?2",
+            (user_id, deck_id),
+        )?;
+        Ok(())
+    }
+
+    fn put_oauth_state(
+        &self,
+        state: &str,
+        provider: &str,
+        created_at: u64,
+    ) -> Result<(), AndyError> {
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO oauth_states (state, provider, created_at) VALUES (?1, ?2, ?3)",
+            (state, provider, created_at),
+        )?;
+        Ok(())
+    }
+
+    fn take_oauth_state(&self, state: &str) -> Result<Option<(String, u64)>, AndyError> {
+        let conn = self.conn.lock().unwrap();
+        let found = {
+            let mut stmt =
+                conn.prepare("SELECT provider, created_at FROM oauth_states WHERE state = ?1")?;
+            let mut rows = stmt.query((state,))?;
+            match rows.next()? {
+                Some(row) => Some((row.get::<_, String>(0)?, row.get::<_, u64>(1)?)),
+                None => None,
+            }
+        };
+
+        if found.is_some() {
+            conn.execute("DELETE FROM oauth_states WHERE state = ?1", (state,))?;
+        }
+
+        Ok(found)
+    }
+}